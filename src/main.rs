@@ -1,15 +1,173 @@
 use std::fmt::Write;
+use std::str::FromStr;
 
 use argh::FromArgs;
 use chrono::offset::TimeZone;
-use chrono::{DateTime, NaiveDate, Utc};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use chrono_tz::Tz;
 use clickhouse_rs::Pool;
+use serde::Serialize;
 use uuid::Uuid;
 
-type Error = Box<dyn std::error::Error>;
+type Error = LookupError;
 
-const CLICKHOUSE_FORMAT: &'static str = "%Y-%m-%d %H:%M:%S";
+const CLICKHOUSE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+fn default_chunk_days() -> u64 {
+    14
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+/// Errors surfaced by outcome lookups, split by failure class so callers can
+/// decide what's worth retrying.
+#[derive(Debug)]
+pub enum LookupError {
+    /// Failed to obtain a connection or lost one mid-query; transient and
+    /// safe to retry.
+    Connection(String),
+    /// ClickHouse rejected the query itself (bad SQL, missing table,
+    /// permission, etc). Not retried.
+    Query(String),
+    /// A row or input value couldn't be decoded into the expected shape.
+    Decode(String),
+    /// The lookup completed without error but found nothing to scope to
+    /// (e.g. no org_id for a project_id).
+    NotFound,
+}
+
+impl std::fmt::Display for LookupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LookupError::Connection(message) => write!(f, "connection error: {}", message),
+            LookupError::Query(message) => write!(f, "query error: {}", message),
+            LookupError::Decode(message) => write!(f, "decode error: {}", message),
+            LookupError::NotFound => write!(f, "not found"),
+        }
+    }
+}
+
+impl std::error::Error for LookupError {}
+
+impl From<clickhouse_rs::errors::Error> for LookupError {
+    fn from(err: clickhouse_rs::errors::Error) -> Self {
+        LookupError::Decode(err.to_string())
+    }
+}
+
+impl From<uuid::Error> for LookupError {
+    fn from(err: uuid::Error) -> Self {
+        LookupError::Decode(err.to_string())
+    }
+}
+
+impl From<chrono::ParseError> for LookupError {
+    fn from(err: chrono::ParseError) -> Self {
+        LookupError::Decode(err.to_string())
+    }
+}
+
+impl From<std::io::Error> for LookupError {
+    fn from(err: std::io::Error) -> Self {
+        LookupError::Decode(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for LookupError {
+    fn from(err: serde_json::Error) -> Self {
+        LookupError::Decode(err.to_string())
+    }
+}
+
+/// Classifies a raw ClickHouse error as connection-class (worth retrying) or
+/// query-class (surfaced immediately), based on the underlying failure.
+fn classify_ch_error(err: clickhouse_rs::errors::Error) -> LookupError {
+    let message = err.to_string();
+    let lower = message.to_lowercase();
+    let is_transient = lower.contains("connection")
+        || lower.contains("broken pipe")
+        || lower.contains("timed out")
+        || lower.contains("timeout")
+        || lower.contains("refused")
+        || lower.contains("reset by peer")
+        || lower.contains("disconnected");
+    if is_transient {
+        LookupError::Connection(message)
+    } else {
+        LookupError::Query(message)
+    }
+}
+
+/// Retries `op` up to `max_retries` times with exponential backoff, but only
+/// for `LookupError::Connection` failures; `Query`/`Decode`/`NotFound`
+/// errors are surfaced immediately.
+async fn with_retry<T, F, Fut>(max_retries: u32, mut op: F) -> Result<T, LookupError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, LookupError>>,
+{
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(LookupError::Connection(_)) if attempt < max_retries => {
+                let backoff_ms = 100u64.saturating_mul(2u64.saturating_pow(attempt));
+                tokio::time::sleep(std::time::Duration::from_millis(backoff_ms)).await;
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Acquires a handle and runs `query` against it, retrying connection-class
+/// failures up to `max_retries` times.
+async fn run_query(
+    pool: &Pool,
+    max_retries: u32,
+    query: &str,
+) -> Result<clickhouse_rs::Block<clickhouse_rs::types::Complex>, LookupError> {
+    with_retry(max_retries, || async {
+        let mut client = pool
+            .get_handle()
+            .await
+            .map_err(|err| LookupError::Connection(err.to_string()))?;
+        let block: clickhouse_rs::Block<clickhouse_rs::types::Complex> = client
+            .query(query)
+            .fetch_all()
+            .await
+            .map_err(classify_ch_error)?;
+        Ok(block)
+    })
+    .await
+}
+
+/// Output format for matched outcome rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            other => Err(format!("unknown format: {}", other)),
+        }
+    }
+}
+
+fn default_format() -> Format {
+    Format::Text
+}
 
 fn get_default_dsn() -> String {
     match std::env::var("OUTCOMES_LOOKUP_DSN") {
@@ -18,8 +176,24 @@ fn get_default_dsn() -> String {
     }
 }
 
-/// Looks up outcomes from the outcomes dataset.
+/// Looks up or serves outcomes from the outcomes dataset.
+#[derive(Debug, FromArgs)]
+struct TopLevel {
+    #[argh(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Lookup(Cli),
+    Serve(ServeArgs),
+}
+
+/// Look up outcomes for one or more event IDs, or summarize matching
+/// outcomes with `--summarize`.
 #[derive(Debug, FromArgs)]
+#[argh(subcommand, name = "lookup")]
 struct Cli {
     /// the DSN for clickhouse to connect to.
     #[argh(option, default = "get_default_dsn()")]
@@ -39,9 +213,48 @@ struct Cli {
     /// the UTC day to narrow the search down to (alternative to to/from)
     #[argh(option)]
     pub day: Option<NaiveDate>,
-    /// the event ID to look up.
+    /// size in days of each scan chunk when `from`/`to` span a wide range. Must be at least 1.
+    #[argh(option, default = "default_chunk_days()")]
+    pub chunk_days: u64,
+    /// maximum retry attempts for transient (connection-class) ClickHouse
+    /// failures, with exponential backoff between attempts.
+    #[argh(option, default = "default_max_retries()")]
+    pub max_retries: u32,
+    /// summarize matching outcomes by outcome/reason instead of looking up a
+    /// single event.
+    #[argh(switch)]
+    pub summarize: bool,
+    /// output format for matched rows: text, json or csv.
+    #[argh(option, default = "default_format()")]
+    pub format: Format,
+    /// path to a file with one event ID per line, merged with any positional
+    /// event IDs.
+    #[argh(option)]
+    pub ids_file: Option<String>,
+    /// the event ID(s) to look up. Required unless `--summarize` is set.
+    /// Accepts more than one for a batch lookup.
     #[argh(positional)]
-    pub event_id: Uuid,
+    pub event_id: Vec<Uuid>,
+}
+
+fn default_addr() -> String {
+    "127.0.0.1:8080".to_string()
+}
+
+/// Serve outcome lookups over HTTP.
+#[derive(Debug, FromArgs)]
+#[argh(subcommand, name = "serve")]
+struct ServeArgs {
+    /// address to bind the HTTP server to.
+    #[argh(option, default = "default_addr()")]
+    pub addr: String,
+    /// the DSN for clickhouse to connect to.
+    #[argh(option, default = "get_default_dsn()")]
+    pub dsn: String,
+    /// maximum retry attempts for transient (connection-class) ClickHouse
+    /// failures, with exponential backoff between attempts.
+    #[argh(option, default = "default_max_retries()")]
+    pub max_retries: u32,
 }
 
 /// Possible outcomes
@@ -55,6 +268,16 @@ pub enum Outcome {
     Unknown(u8),
 }
 
+/// Escapes `value` for use as a single CSV field per RFC 4180: quotes it and
+/// doubles any embedded quotes if it contains a comma, quote, or newline.
+fn csv_field(value: &str) -> String {
+    if value.contains(['"', ',', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
 struct OptFormat<T>(Option<T>);
 
 impl<T: std::fmt::Debug> std::fmt::Display for OptFormat<T> {
@@ -79,46 +302,70 @@ impl From<u8> for Outcome {
     }
 }
 
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// A single matched outcome row, in a shape suitable for machine-readable
+/// output formats.
+#[derive(Debug, Serialize)]
+struct OutcomeRow {
+    event_id: Option<Uuid>,
+    project_id: u64,
+    org_id: u64,
+    key_id: Option<u64>,
+    timestamp: DateTime<Utc>,
+    outcome: String,
+    reason: Option<String>,
+}
+
+/// One outcome/reason bucket from `--summarize`, with its matching row count.
+#[derive(Debug, Serialize)]
+struct SummaryRow {
+    outcome: String,
+    reason: Option<String>,
+    count: u64,
+}
+
 /// Given a project id makes a fast scan for the org id.
-async fn find_org_id(pool: &Pool, project_id: u64) -> Result<Option<u64>, Error> {
-    let mut client = pool.get_handle().await?;
-
-    let block = client
-        .query(format!(
-            "select org_id from outcomes_raw_local prewhere project_id = {} where org_id != 0 limit 1",
-            project_id
-        ))
-        .fetch_all()
-        .await?;
+async fn find_org_id(pool: &Pool, project_id: u64, max_retries: u32) -> Result<Option<u64>, Error> {
+    let query = format!(
+        "select org_id from outcomes_raw_local prewhere project_id = {} where org_id != 0 limit 1",
+        project_id
+    );
+    let block = run_query(pool, max_retries, &query).await?;
 
     Ok(if let Some(row) = block.rows().next() {
-        let org_id: u64 = row.get("org_id")?;
+        let org_id: u64 = row.get("org_id").map_err(|err| LookupError::Decode(err.to_string()))?;
         Some(org_id)
     } else {
         None
     })
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli: Cli = argh::from_env();
-
-    let pool = Pool::new(cli.dsn);
-
-    let mut client = pool.get_handle().await?;
+/// Resolves `org_id`/`project_id` into `prewhere` predicates, looking up the
+/// project's org_id (one extra scan) only when `org_id` wasn't given
+/// directly.
+async fn resolve_scope(
+    pool: &Pool,
+    org_id: Option<u64>,
+    project_id: Option<u64>,
+    max_retries: u32,
+) -> Result<Vec<String>, Error> {
     let mut prewhere = vec![];
-    let mut where_ = vec![];
 
-    if let Some(project_id) = cli.project_id {
+    if let Some(project_id) = project_id {
         prewhere.push(format!("project_id = {}", project_id));
     }
 
-    let org_id = match (cli.org_id, cli.project_id) {
+    let org_id = match (org_id, project_id) {
         (Some(org_id), _) => Some(org_id),
         (None, Some(project_id)) => Some(
-            find_org_id(&pool, project_id)
+            find_org_id(pool, project_id, max_retries)
                 .await?
-                .ok_or("could not find org_id for project_id")?,
+                .ok_or(LookupError::NotFound)?,
         ),
         _ => None,
     };
@@ -126,25 +373,298 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         prewhere.push(format!("org_id = {}", org_id));
     }
 
-    let (from, to) = if let Some(day) = cli.day.map(|x| Utc.from_utc_date(&x)) {
+    Ok(prewhere)
+}
+
+/// Parameters scoping a single event_id lookup.
+#[derive(Debug, Clone)]
+struct LookupParams {
+    pub event_id: Uuid,
+    pub org_id: Option<u64>,
+    pub project_id: Option<u64>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Looks up every outcome row matching `params`, shared by the one-shot CLI
+/// path and the `serve` HTTP handler.
+async fn lookup(
+    pool: &Pool,
+    max_retries: u32,
+    params: LookupParams,
+) -> Result<Vec<OutcomeRow>, Error> {
+    let mut prewhere = resolve_scope(pool, params.org_id, params.project_id, max_retries).await?;
+
+    if let Some(from) = params.from {
+        prewhere.push(format!("timestamp >= '{}'", from.format(CLICKHOUSE_FORMAT)));
+    }
+    if let Some(to) = params.to {
+        prewhere.push(format!("timestamp < '{}'", to.format(CLICKHOUSE_FORMAT)));
+    }
+
+    let where_ = vec![format!("event_id = '{}'", params.event_id)];
+    let query = build_query(&prewhere, &where_);
+    let block = run_query(pool, max_retries, &query).await?;
+    decode_rows(block)
+}
+
+/// Reads event IDs from `cli.event_id` and, if given, `cli.ids_file` (one
+/// UUID per line), deduplicated.
+fn collect_event_ids(cli: &Cli) -> Result<Vec<Uuid>, Error> {
+    let mut ids = cli.event_id.clone();
+
+    if let Some(path) = &cli.ids_file {
+        let contents = std::fs::read_to_string(path)?;
+        for line in contents.lines() {
+            let line = line.trim();
+            if !line.is_empty() {
+                ids.push(line.parse()?);
+            }
+        }
+    }
+
+    ids.sort();
+    ids.dedup();
+    Ok(ids)
+}
+
+/// The distinct `(timestamp, outcome, reason)` sightings seen for one
+/// `event_id`, each mapped to how many raw rows backed it.
+/// `outcomes_raw_local` is a per-node local table, so the same logical
+/// outcome can be emitted/seen on multiple replicas.
+#[derive(Debug, Default)]
+struct EventSightings {
+    pub sightings: std::collections::BTreeMap<(DateTime<Utc>, String, Option<String>), u64>,
+}
+
+/// One distinct sighting for a batch-looked-up `event_id`, and how many raw
+/// rows backed it.
+#[derive(Debug, Serialize)]
+struct EventSighting {
+    timestamp: DateTime<Utc>,
+    outcome: String,
+    reason: Option<String>,
+    count: u64,
+}
+
+/// The sightings found for one `event_id` in a batch lookup.
+#[derive(Debug, Serialize)]
+struct BatchEvent {
+    event_id: Uuid,
+    sightings: Vec<EventSighting>,
+}
+
+/// Looks up every outcome row for any of `ids` in a single round-trip and
+/// merges duplicate sightings of the same `event_id` together.
+async fn lookup_batch(
+    pool: &Pool,
+    max_retries: u32,
+    ids: &[Uuid],
+    org_id: Option<u64>,
+    project_id: Option<u64>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<std::collections::BTreeMap<Uuid, EventSightings>, Error> {
+    let mut prewhere = resolve_scope(pool, org_id, project_id, max_retries).await?;
+
+    if let Some(from) = from {
+        prewhere.push(format!("timestamp >= '{}'", from.format(CLICKHOUSE_FORMAT)));
+    }
+    if let Some(to) = to {
+        prewhere.push(format!("timestamp < '{}'", to.format(CLICKHOUSE_FORMAT)));
+    }
+
+    let ids_list = ids
+        .iter()
+        .map(|id| format!("'{}'", id))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let where_ = vec![format!("event_id IN ({})", ids_list)];
+    let query = build_query(&prewhere, &where_);
+    let block = run_query(pool, max_retries, &query).await?;
+
+    let mut result: std::collections::BTreeMap<Uuid, EventSightings> = Default::default();
+    for row in decode_rows(block)? {
+        let Some(event_id) = row.event_id else {
+            continue;
+        };
+        let entry = result.entry(event_id).or_default();
+        *entry
+            .sightings
+            .entry((row.timestamp, row.outcome, row.reason))
+            .or_insert(0) += 1;
+    }
+    Ok(result)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let top: TopLevel = argh::from_env();
+    let cli = match top.command {
+        Command::Serve(serve_args) => return run_serve(serve_args).await,
+        Command::Lookup(cli) => cli,
+    };
+
+    if cli.chunk_days == 0 {
+        return Err("--chunk-days must be at least 1".into());
+    }
+
+    let pool = Pool::new(cli.dsn.clone());
+
+    let (from, to) = if let Some(day) = cli.day {
+        let start = day.and_hms_opt(0, 0, 0).expect("midnight is always valid");
+        let next_day = day
+            .succ_opt()
+            .expect("day is not chrono::NaiveDate::MAX")
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight is always valid");
         (
-            Some(day.and_hms(0, 0, 0)),
-            Some(day.succ().and_hms(0, 0, 0)),
+            Some(Utc.from_utc_datetime(&start)),
+            Some(Utc.from_utc_datetime(&next_day)),
         )
     } else {
         (cli.from, cli.to)
     };
 
-    if let Some(from) = from {
-        prewhere.push(format!("timestamp >= '{}'", from.format(CLICKHOUSE_FORMAT)));
+    if cli.summarize {
+        let mut prewhere = resolve_scope(&pool, cli.org_id, cli.project_id, cli.max_retries).await?;
+        if let Some(from) = from {
+            prewhere.push(format!("timestamp >= '{}'", from.format(CLICKHOUSE_FORMAT)));
+        }
+        if let Some(to) = to {
+            prewhere.push(format!("timestamp < '{}'", to.format(CLICKHOUSE_FORMAT)));
+        }
+
+        let mut query = "select outcome, reason, count() as c from outcomes_raw_local"
+            .to_string();
+        if !prewhere.is_empty() {
+            write!(&mut query, " prewhere {}", prewhere.join(" and ")).unwrap();
+        }
+        write!(&mut query, " group by outcome, reason order by c desc").unwrap();
+
+        let block = run_query(&pool, cli.max_retries, &query).await?;
+        let mut summary = vec![];
+        for row in block.rows() {
+            let outcome_raw: u8 = row.get("outcome")?;
+            let reason: Option<String> = row.get("reason")?;
+            let count: u64 = row.get("c")?;
+            let outcome: Outcome = outcome_raw.into();
+            summary.push(SummaryRow {
+                outcome: outcome.to_string(),
+                reason,
+                count,
+            });
+        }
+        render_summary(cli.format, summary)?;
+        return Ok(());
     }
 
-    if let Some(to) = to {
-        prewhere.push(format!("timestamp < '{}'", to.format(CLICKHOUSE_FORMAT)));
+    let ids = collect_event_ids(&cli)?;
+    if ids.is_empty() {
+        return Err("at least one event_id is required unless --summarize is set".into());
+    }
+
+    if ids.len() > 1 {
+        let by_event = lookup_batch(
+            &pool,
+            cli.max_retries,
+            &ids,
+            cli.org_id,
+            cli.project_id,
+            from,
+            to,
+        )
+        .await?;
+        let batch_events = ids
+            .iter()
+            .map(|id| {
+                let sightings = by_event
+                    .get(id)
+                    .map(|s| {
+                        s.sightings
+                            .iter()
+                            .map(|((timestamp, outcome, reason), &count)| EventSighting {
+                                timestamp: *timestamp,
+                                outcome: outcome.clone(),
+                                reason: reason.clone(),
+                                count,
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                BatchEvent {
+                    event_id: *id,
+                    sightings,
+                }
+            })
+            .collect();
+        render_batch(cli.format, batch_events)?;
+        return Ok(());
     }
 
-    where_.push(format!("event_id = '{}'", cli.event_id));
+    let event_id = ids[0];
+    let interval = Duration::days(cli.chunk_days as i64);
+
+    let mut found = false;
+
+    if let (Some(from), Some(to)) = (from, to) {
+        if to - from > interval {
+            // Wide window: scan chunk by chunk so a single query never has to
+            // buffer the whole span, and stop as soon as we have a hit.
+            let prewhere = resolve_scope(&pool, cli.org_id, cli.project_id, cli.max_retries).await?;
+            let where_ = vec![format!("event_id = '{}'", event_id)];
+
+            let mut current_from = from;
+            while current_from < to && !found {
+                let current_to = std::cmp::min(current_from + interval, to);
 
+                let mut chunk_prewhere = prewhere.clone();
+                chunk_prewhere.push(format!(
+                    "timestamp >= '{}'",
+                    current_from.format(CLICKHOUSE_FORMAT)
+                ));
+                chunk_prewhere.push(format!(
+                    "timestamp < '{}'",
+                    current_to.format(CLICKHOUSE_FORMAT)
+                ));
+
+                let query = build_query(&chunk_prewhere, &where_);
+                let block = run_query(&pool, cli.max_retries, &query).await?;
+                found |= print_rows(cli.format, block)?;
+
+                current_from = current_to;
+            }
+
+            if !found {
+                print_no_rows(cli.format);
+            }
+            return Ok(());
+        }
+    }
+
+    let rows = lookup(
+        &pool,
+        cli.max_retries,
+        LookupParams {
+            event_id,
+            org_id: cli.org_id,
+            project_id: cli.project_id,
+            from,
+            to,
+        },
+    )
+    .await?;
+    found = render_rows(cli.format, rows)?;
+
+    if !found {
+        print_no_rows(cli.format);
+    }
+    Ok(())
+}
+
+/// Builds the `select * from outcomes_raw_local ...` query from the given
+/// prewhere/where predicates.
+fn build_query(prewhere: &[String], where_: &[String]) -> String {
     let mut query = "select * from outcomes_raw_local".to_string();
     if !prewhere.is_empty() {
         write!(&mut query, " prewhere {}", prewhere.join(" and ")).unwrap();
@@ -152,10 +672,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     if !where_.is_empty() {
         write!(&mut query, " where {}", where_.join(" and ")).unwrap();
     }
+    query
+}
 
-    let block = client.query(&query).fetch_all().await?;
-
-    let mut found = false;
+/// Decodes every row in `block` into an `OutcomeRow`.
+fn decode_rows(block: clickhouse_rs::Block<clickhouse_rs::types::Complex>) -> Result<Vec<OutcomeRow>, Error> {
+    let mut rows = vec![];
     for row in block.rows() {
         let event_id: Option<Uuid> = row.get("event_id")?;
         let project_id: u64 = row.get("project_id")?;
@@ -165,18 +687,254 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let outcome_raw: u8 = row.get("outcome")?;
         let reason: Option<String> = row.get("reason")?;
         let outcome: Outcome = outcome_raw.into();
-        println!("event_id: {}", OptFormat(event_id));
-        println!("project_id: {}", project_id);
-        println!("org_id: {}", org_id);
-        println!("key_id: {}", OptFormat(key_id));
-        println!("timestamp: {}", timestamp);
-        println!("outcome: {:?}", outcome);
-        println!("reason: {}", OptFormat(reason));
-        found = true;
+        rows.push(OutcomeRow {
+            event_id,
+            project_id,
+            org_id,
+            key_id,
+            timestamp: timestamp.with_timezone(&Utc),
+            outcome: outcome.to_string(),
+            reason,
+        });
     }
+    Ok(rows)
+}
 
-    if !found {
-        println!("no outcomes found");
+/// Serializes `rows` to JSON the same way across the CLI and the HTTP
+/// server: a bare object for a single row, an array otherwise.
+fn rows_to_json<T: Serialize>(rows: &[T]) -> serde_json::Result<String> {
+    if rows.len() == 1 {
+        serde_json::to_string(&rows[0])
+    } else {
+        serde_json::to_string(rows)
+    }
+}
+
+/// Reports a no-match result in the given `format`, so `--format json`/`csv`
+/// consumers still get valid output instead of a stray text line.
+fn print_no_rows(format: Format) {
+    match format {
+        Format::Text => println!("no outcomes found"),
+        Format::Json => println!("[]"),
+        Format::Csv => println!("event_id,project_id,org_id,key_id,timestamp,outcome,reason"),
+    }
+}
+
+/// Renders `rows` in the given `format` and returns whether any row was
+/// found.
+fn render_rows(format: Format, rows: Vec<OutcomeRow>) -> Result<bool, Error> {
+    if rows.is_empty() {
+        return Ok(false);
+    }
+
+    match format {
+        Format::Text => {
+            for row in &rows {
+                println!("event_id: {}", OptFormat(row.event_id));
+                println!("project_id: {}", row.project_id);
+                println!("org_id: {}", row.org_id);
+                println!("key_id: {}", OptFormat(row.key_id));
+                println!("timestamp: {}", row.timestamp.to_rfc3339());
+                println!("outcome: {}", row.outcome);
+                println!("reason: {}", OptFormat(row.reason.clone()));
+            }
+        }
+        Format::Json => {
+            println!("{}", rows_to_json(&rows)?);
+        }
+        Format::Csv => {
+            println!("event_id,project_id,org_id,key_id,timestamp,outcome,reason");
+            for row in &rows {
+                let event_id = row.event_id.map_or_else(|| "-".to_string(), |id| id.to_string());
+                let key_id = row.key_id.map_or_else(|| "-".to_string(), |id| id.to_string());
+                let reason = row.reason.as_deref().unwrap_or("-");
+                println!(
+                    "{},{},{},{},{},{},{}",
+                    csv_field(&event_id),
+                    row.project_id,
+                    row.org_id,
+                    csv_field(&key_id),
+                    row.timestamp.to_rfc3339(),
+                    csv_field(&row.outcome),
+                    csv_field(reason),
+                );
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+/// Renders `--summarize` buckets in the given `format`.
+fn render_summary(format: Format, rows: Vec<SummaryRow>) -> Result<(), Error> {
+    match format {
+        Format::Text => {
+            for row in &rows {
+                println!("{:>8}  {:<12}  {}", row.count, row.outcome, OptFormat(row.reason.clone()));
+            }
+        }
+        Format::Json => {
+            // Unlike `rows_to_json`, always emit an array here: summarize
+            // output is inherently a list of buckets, even when there's
+            // only one, so callers shouldn't have to special-case a bare
+            // object.
+            println!("{}", serde_json::to_string(&rows)?);
+        }
+        Format::Csv => {
+            println!("outcome,reason,count");
+            for row in &rows {
+                let reason = row.reason.as_deref().unwrap_or("-");
+                println!("{},{},{}", csv_field(&row.outcome), csv_field(reason), row.count);
+            }
+        }
     }
     Ok(())
 }
+
+/// Renders the results of a batch lookup in the given `format`. Text and
+/// JSON output have one entry per requested `event_id` (including those with
+/// no sightings); CSV output has one line per sighting, so an `event_id`
+/// with no sightings has no line there.
+fn render_batch(format: Format, events: Vec<BatchEvent>) -> Result<(), Error> {
+    match format {
+        Format::Text => {
+            for event in &events {
+                println!("event_id: {}", event.event_id);
+                if event.sightings.is_empty() {
+                    println!("  no outcomes found");
+                    continue;
+                }
+                for sighting in &event.sightings {
+                    println!(
+                        "  seen_on: timestamp={} outcome={} reason={} count={}",
+                        sighting.timestamp.to_rfc3339(),
+                        sighting.outcome,
+                        OptFormat(sighting.reason.clone()),
+                        sighting.count,
+                    );
+                }
+            }
+        }
+        Format::Json => {
+            println!("{}", rows_to_json(&events)?);
+        }
+        Format::Csv => {
+            println!("event_id,timestamp,outcome,reason,count");
+            for event in &events {
+                for sighting in &event.sightings {
+                    let reason = sighting.reason.as_deref().unwrap_or("-");
+                    println!(
+                        "{},{},{},{},{}",
+                        event.event_id,
+                        sighting.timestamp.to_rfc3339(),
+                        csv_field(&sighting.outcome),
+                        csv_field(reason),
+                        sighting.count,
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decodes and renders every row in `block`, returning whether any row was
+/// found.
+fn print_rows(format: Format, block: clickhouse_rs::Block<clickhouse_rs::types::Complex>) -> Result<bool, Error> {
+    render_rows(format, decode_rows(block)?)
+}
+
+/// Runs the `serve` subcommand: keeps a connection pool alive and exposes
+/// `GET /outcomes/{event_id}` over HTTP so dashboards and support tooling
+/// don't have to pay connection-pool warmup on every invocation.
+async fn run_serve(args: ServeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let pool = Pool::new(args.dsn);
+    let addr: std::net::SocketAddr = args.addr.parse()?;
+
+    let max_retries = args.max_retries;
+    let make_svc = hyper::service::make_service_fn(move |_conn| {
+        let pool = pool.clone();
+        async move {
+            Ok::<_, std::convert::Infallible>(hyper::service::service_fn(move |req| {
+                handle_request(pool.clone(), max_retries, req)
+            }))
+        }
+    });
+
+    eprintln!("listening on {}", addr);
+    hyper::Server::bind(&addr).serve(make_svc).await?;
+    Ok(())
+}
+
+/// Handles a single `GET /outcomes/{event_id}` request.
+async fn handle_request(
+    pool: Pool,
+    max_retries: u32,
+    req: hyper::Request<hyper::Body>,
+) -> Result<hyper::Response<hyper::Body>, std::convert::Infallible> {
+    let event_id = req
+        .uri()
+        .path()
+        .strip_prefix("/outcomes/")
+        .and_then(|s| Uuid::parse_str(s).ok());
+
+    let event_id = match event_id {
+        Some(event_id) => event_id,
+        None => {
+            return Ok(hyper::Response::builder()
+                .status(hyper::StatusCode::NOT_FOUND)
+                .body(hyper::Body::from("not found"))
+                .unwrap());
+        }
+    };
+
+    let params = parse_lookup_query(req.uri().query().unwrap_or(""), event_id);
+
+    match lookup(&pool, max_retries, params).await {
+        Ok(rows) => Ok(hyper::Response::builder()
+            .header("content-type", "application/json")
+            .body(hyper::Body::from(
+                rows_to_json(&rows).unwrap_or_else(|_| "[]".to_string()),
+            ))
+            .unwrap()),
+        Err(err) => Ok(hyper::Response::builder()
+            .status(hyper::StatusCode::INTERNAL_SERVER_ERROR)
+            .body(hyper::Body::from(err.to_string()))
+            .unwrap()),
+    }
+}
+
+/// Parses `org_id`, `project_id`, `from` and `to` query parameters into a
+/// `LookupParams` for the given `event_id`.
+fn parse_lookup_query(query: &str, event_id: Uuid) -> LookupParams {
+    let mut params = LookupParams {
+        event_id,
+        org_id: None,
+        project_id: None,
+        from: None,
+        to: None,
+    };
+
+    for pair in query.split('&') {
+        let mut parts = pair.splitn(2, '=');
+        let key = parts.next().unwrap_or("");
+        let value = parts.next().unwrap_or("");
+        match key {
+            "org_id" => params.org_id = value.parse().ok(),
+            "project_id" => params.project_id = value.parse().ok(),
+            "from" => {
+                params.from = DateTime::parse_from_rfc3339(value)
+                    .ok()
+                    .map(|d| d.with_timezone(&Utc))
+            }
+            "to" => {
+                params.to = DateTime::parse_from_rfc3339(value)
+                    .ok()
+                    .map(|d| d.with_timezone(&Utc))
+            }
+            _ => {}
+        }
+    }
+
+    params
+}